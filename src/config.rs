@@ -0,0 +1,224 @@
+//! TOML-backed startup configuration.
+
+use serde::Deserialize;
+use std::fmt;
+use std::path::Path;
+
+use crate::{Palette, DEFAULT_HEIGHT, DEFAULT_WIDTH};
+
+const CONFIG_PATH: &str = "mandelbrot.toml";
+
+#[derive(Debug, Default, Deserialize)]
+struct RawConfig {
+    scale: Option<f64>,
+    x_offset: Option<f64>,
+    y_offset: Option<f64>,
+    iteration_number: Option<u16>,
+    width: Option<u32>,
+    height: Option<u32>,
+    palette: Option<String>,
+    zoom_in_factor: Option<f64>,
+    zoom_out_factor: Option<f64>,
+    pan_sensitivity: Option<f64>,
+}
+
+#[derive(Debug)]
+struct InvalidField {
+    field: &'static str,
+}
+
+impl fmt::Display for InvalidField {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "`{}` is out of range, using the default", self.field)
+    }
+}
+
+impl std::error::Error for InvalidField {}
+
+pub(crate) struct Config {
+    pub scale: f64,
+    pub x_offset: f64,
+    pub y_offset: f64,
+    pub iteration_number: u16,
+    pub width: u32,
+    pub height: u32,
+    pub palette: Palette,
+    pub zoom_in_factor: f64,
+    pub zoom_out_factor: f64,
+    pub pan_sensitivity: f64,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            scale: 0.5,
+            x_offset: 0.0,
+            y_offset: 0.0,
+            iteration_number: 255,
+            width: DEFAULT_WIDTH,
+            height: DEFAULT_HEIGHT,
+            palette: Palette::Cyclic,
+            zoom_in_factor: 1.1,
+            zoom_out_factor: 0.9,
+            pan_sensitivity: 1.0,
+        }
+    }
+}
+
+impl Config {
+    /// Loads `mandelbrot.toml`, falling back to defaults when invalid.
+    pub(crate) fn load() -> Self {
+        Self::load_from(Path::new(CONFIG_PATH))
+    }
+
+    fn load_from(path: &Path) -> Self {
+        let raw = match std::fs::read_to_string(path) {
+            Ok(contents) => toml::from_str(&contents).unwrap_or_else(|err| {
+                crate::log_error("config::parse", err);
+                RawConfig::default()
+            }),
+            Err(_) => RawConfig::default(),
+        };
+
+        let defaults = Config::default();
+        Config {
+            scale: validated(raw.scale, defaults.scale, "scale", |v| v != 0.0),
+            x_offset: raw.x_offset.unwrap_or(defaults.x_offset),
+            y_offset: raw.y_offset.unwrap_or(defaults.y_offset),
+            iteration_number: validated(
+                raw.iteration_number,
+                defaults.iteration_number,
+                "iteration_number",
+                |v| v > 0,
+            ),
+            width: validated(raw.width, defaults.width, "width", |v| v > 0),
+            height: validated(raw.height, defaults.height, "height", |v| v > 0),
+            palette: raw
+                .palette
+                .as_deref()
+                .map(parse_palette)
+                .unwrap_or(Some(defaults.palette))
+                .unwrap_or_else(|| {
+                    crate::log_error("config::parse", InvalidField { field: "palette" });
+                    defaults.palette
+                }),
+            zoom_in_factor: validated(
+                raw.zoom_in_factor,
+                defaults.zoom_in_factor,
+                "zoom_in_factor",
+                |v| v > 1.0,
+            ),
+            zoom_out_factor: validated(
+                raw.zoom_out_factor,
+                defaults.zoom_out_factor,
+                "zoom_out_factor",
+                |v| v > 0.0 && v < 1.0,
+            ),
+            pan_sensitivity: validated(
+                raw.pan_sensitivity,
+                defaults.pan_sensitivity,
+                "pan_sensitivity",
+                |v| v > 0.0,
+            ),
+        }
+    }
+}
+
+fn parse_palette(name: &str) -> Option<Palette> {
+    match name {
+        "grayscale" => Some(Palette::Grayscale),
+        "cyclic" => Some(Palette::Cyclic),
+        _ => None,
+    }
+}
+
+fn validated<T: Copy>(value: Option<T>, default: T, field: &'static str, is_valid: impl Fn(T) -> bool) -> T {
+    match value {
+        Some(v) if is_valid(v) => v,
+        Some(_) => {
+            crate::log_error("config::validate", InvalidField { field });
+            default
+        }
+        None => default,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    struct TempConfigFile {
+        path: std::path::PathBuf,
+    }
+
+    impl TempConfigFile {
+        fn new(contents: &str) -> Self {
+            static COUNTER: AtomicU32 = AtomicU32::new(0);
+            let n = COUNTER.fetch_add(1, Ordering::Relaxed);
+            let path = std::env::temp_dir().join(format!(
+                "mandelbrot-config-test-{}-{n}.toml",
+                std::process::id()
+            ));
+            std::fs::write(&path, contents).unwrap();
+            Self { path }
+        }
+    }
+
+    impl Drop for TempConfigFile {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_file(&self.path);
+        }
+    }
+
+    #[test]
+    fn missing_file_falls_back_to_defaults() {
+        let path = std::env::temp_dir().join("mandelbrot-config-test-missing.toml");
+        let _ = std::fs::remove_file(&path);
+
+        let config = Config::load_from(&path);
+        let defaults = Config::default();
+        assert_eq!(config.scale, defaults.scale);
+        assert_eq!(config.palette, defaults.palette);
+    }
+
+    #[test]
+    fn empty_file_falls_back_to_defaults() {
+        let file = TempConfigFile::new("");
+
+        let config = Config::load_from(&file.path);
+        assert_eq!(config.iteration_number, Config::default().iteration_number);
+    }
+
+    #[test]
+    fn bad_palette_name_falls_back_to_default_palette() {
+        let file = TempConfigFile::new("palette = \"rainbow\"\n");
+
+        let config = Config::load_from(&file.path);
+        assert_eq!(config.palette, Palette::Cyclic);
+    }
+
+    #[test]
+    fn valid_palette_name_is_used() {
+        let file = TempConfigFile::new("palette = \"grayscale\"\n");
+
+        let config = Config::load_from(&file.path);
+        assert_eq!(config.palette, Palette::Grayscale);
+    }
+
+    #[test]
+    fn out_of_range_zoom_in_factor_falls_back_to_default() {
+        let file = TempConfigFile::new("zoom_in_factor = 0.5\n");
+
+        let config = Config::load_from(&file.path);
+        assert_eq!(config.zoom_in_factor, Config::default().zoom_in_factor);
+    }
+
+    #[test]
+    fn zero_scale_falls_back_to_default() {
+        let file = TempConfigFile::new("scale = 0.0\n");
+
+        let config = Config::load_from(&file.path);
+        assert_eq!(config.scale, Config::default().scale);
+    }
+}
@@ -1,64 +1,208 @@
 #![deny(clippy::all)]
 #![forbid(unsafe_code)]
-#![windows_subsystem = "windows"]
+#![cfg_attr(not(target_arch = "wasm32"), windows_subsystem = "windows")]
 
 use error_iter::ErrorIter as _;
 use log::error;
 use pixels::{Error, Pixels, SurfaceTexture};
 use winit::dpi::LogicalSize;
-use winit::event::{Event};
+use winit::event::{Event, WindowEvent};
 use winit::event_loop::{ControlFlow, EventLoop};
-use winit::window::WindowBuilder;
+use winit::window::{Window, WindowBuilder};
 use winit_input_helper::WinitInputHelper;
 
 use rayon::prelude::*;
+use std::time::Duration;
 
-const WIDTH:  u32 = 1024;
-const HEIGHT: u32 = 1024;
+// std::time::Instant panics on wasm32-unknown-unknown; instant::Instant is
+// API-compatible and backed by Performance.now() there.
+#[cfg(not(target_arch = "wasm32"))]
+use std::time::Instant;
+#[cfg(target_arch = "wasm32")]
+use instant::Instant;
 
-const HALF_WIDTH: u32 = WIDTH/2;
-const HALF_HEIGHT: u32 = HEIGHT/2;
+#[cfg(target_arch = "wasm32")]
+use pixels::PixelsBuilder;
+#[cfg(target_arch = "wasm32")]
+use wasm_bindgen::prelude::*;
+#[cfg(target_arch = "wasm32")]
+use winit::platform::web::WindowExtWebSys;
+
+mod config;
+use config::Config;
+
+const DEFAULT_WIDTH:  u32 = 1024;
+const DEFAULT_HEIGHT: u32 = 1024;
+
+#[derive(Debug)]
+enum AppError {
+    Pixels(Error),
+    Web(String),
+}
+
+impl std::fmt::Display for AppError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AppError::Pixels(err) => write!(f, "{err}"),
+            AppError::Web(msg) => write!(f, "{msg}"),
+        }
+    }
+}
+
+impl std::error::Error for AppError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            AppError::Pixels(err) => Some(err),
+            AppError::Web(_) => None,
+        }
+    }
+}
+
+impl From<Error> for AppError {
+    fn from(err: Error) -> Self {
+        AppError::Pixels(err)
+    }
+}
+
+// Target frame budget for the adaptive iteration cap.
+const TARGET_FRAME_BUDGET: Duration = Duration::from_millis(33);
 
 struct World {
+    // Backing buffer size, in physical pixels.
+    width: u32,
+    height: u32,
+    // Physical-per-logical pixel ratio; draw() divides by this.
+    scale_factor: f64,
     iteration_number: u16,
     scale: f64,
     x_offset: f64,
     y_offset: f64,
+    // Escape radius for the smooth-coloring formula.
+    bailout: f64,
+    palette: Palette,
+    zoom_in_factor: f64,
+    zoom_out_factor: f64,
+    pan_sensitivity: f64,
+    // Bounds the iteration cap floats between.
+    min_iteration_number: u16,
+    max_iteration_number: u16,
+    last_frame: Instant,
+    fps: f64,
 }
 
-fn main() -> Result<(), Error> {
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum Palette {
+    Grayscale,
+    Cyclic,
+}
+
+impl Palette {
+    // RGB control points the cyclic palette interpolates between.
+    const CYCLIC_STOPS: [[f64; 3]; 5] = [
+        [0.0, 0.027, 0.392],
+        [0.125, 0.420, 0.796],
+        [0.929, 1.0, 1.0],
+        [1.0, 0.667, 0.0],
+        [0.0, 0.027, 0.392],
+    ];
+
+    fn color(&self, mu: f64) -> [u8; 3] {
+        match self {
+            Palette::Grayscale => {
+                let v = (mu * 4.0).clamp(0.0, 255.0) as u8;
+                [v, v, v]
+            }
+            Palette::Cyclic => {
+                let stops = Self::CYCLIC_STOPS;
+                let t = (mu * 0.05).rem_euclid((stops.len() - 1) as f64);
+                let i = t.floor() as usize;
+                let frac = t - i as f64;
+                let a = stops[i];
+                let b = stops[i + 1];
+                [
+                    (lerp(a[0], b[0], frac) * 255.0) as u8,
+                    (lerp(a[1], b[1], frac) * 255.0) as u8,
+                    (lerp(a[2], b[2], frac) * 255.0) as u8,
+                ]
+            }
+        }
+    }
+}
+
+fn lerp(a: f64, b: f64, t: f64) -> f64 {
+    a + (b - a) * t
+}
+
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen(start)]
+pub fn start() -> Result<(), JsValue> {
+    std::panic::set_hook(Box::new(console_error_panic_hook::hook));
+    console_log::init_with_level(log::Level::Warn).map_err(|e| JsValue::from_str(&e.to_string()))?;
+
+    wasm_bindgen_futures::future_to_promise(async move {
+        run().await.map_err(|e| JsValue::from_str(&e.to_string()))?;
+        Ok(JsValue::UNDEFINED)
+    });
+
+    Ok(())
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn main() -> Result<(), AppError> {
     env_logger::init();
+    pollster::block_on(run())
+}
+
+async fn run() -> Result<(), AppError> {
+    let config = Config::load();
     let event_loop = EventLoop::new();
     let mut input = WinitInputHelper::new();
-    let window = {
-        let size = LogicalSize::new(WIDTH as f64, HEIGHT as f64);
-        WindowBuilder::new()
-            .with_title("Mandelbrot")
-            .with_inner_size(size)
-            .with_min_inner_size(size)
-            .build(&event_loop)
-            .unwrap()
-    };
-
-    let mut pixels = {
-        let window_size = window.inner_size();
-        let surface_texture = SurfaceTexture::new(window_size.width, window_size.height, &window);
-        Pixels::new(WIDTH, HEIGHT, surface_texture)?
-    };
-    let mut world = World::new();
+    #[cfg(not(target_arch = "wasm32"))]
+    let window = build_window(&event_loop, &config);
+    #[cfg(target_arch = "wasm32")]
+    let window = build_window(&event_loop, &config)?;
+
+    let mut pixels = build_pixels(&window).await?;
+    let window_size = window.inner_size();
+    let mut world = World::new(window_size.width, window_size.height, window.scale_factor(), &config);
 
     event_loop.run(move |event, _, control_flow| {
         // Draw the current frame
         if let Event::RedrawRequested(_) = event {
+            world.begin_frame();
             world.draw(pixels.frame_mut());
             if let Err(err) = pixels.render() {
                 log_error("pixels.render", err);
                 *control_flow = ControlFlow::Exit;
                 return;
             }
+            let cursor = input
+                .mouse()
+                .map(|(x, y)| world.screen_to_complex(x as f64, y as f64));
+            window.set_title(&world.status_line(cursor));
+        }
+
+        // Scale factor changed
+        if let Event::WindowEvent {
+            event: WindowEvent::ScaleFactorChanged { scale_factor, new_inner_size },
+            ..
+        } = &event
+        {
+            world.scale_factor = *scale_factor;
+            if let Err(err) = pixels.resize_surface(new_inner_size.width, new_inner_size.height) {
+                log_error("pixels.resize_surface", err);
+                *control_flow = ControlFlow::Exit;
+                return;
+            }
+            if let Err(err) = pixels.resize_buffer(new_inner_size.width, new_inner_size.height) {
+                log_error("pixels.resize_buffer", err);
+                *control_flow = ControlFlow::Exit;
+                return;
+            }
+            world.width = new_inner_size.width;
+            world.height = new_inner_size.height;
         }
 
-        
         // Handle input events
         if input.update(&event) {
             // Close events
@@ -74,20 +218,31 @@ fn main() -> Result<(), Error> {
                     *control_flow = ControlFlow::Exit;
                     return;
                 }
+                if let Err(err) = pixels.resize_buffer(size.width, size.height) {
+                    log_error("pixels.resize_buffer", err);
+                    *control_flow = ControlFlow::Exit;
+                    return;
+                }
+                world.width = size.width;
+                world.height = size.height;
             }
-            
+
             let scroll_diff = input.scroll_diff();
             if scroll_diff != 0.0 {
                 if scroll_diff < 0.0 {
-                    world.scale *= 0.9;
+                    world.scale *= world.zoom_out_factor;
                 }  else {
-                    world.scale *= 1.1;
+                    world.scale *= world.zoom_in_factor;
                 }
             }
-            
+
             if input.mouse_held(0) {
-                world.x_offset -= (input.mouse_diff().0 as f64/HALF_WIDTH  as f64/world.scale) as f64;
-                world.y_offset -= (input.mouse_diff().1 as f64/HALF_HEIGHT as f64/world.scale) as f64;
+                let half_width = world.logical_width() / 2.0;
+                let half_height = world.logical_height() / 2.0;
+                let dx = input.mouse_diff().0 as f64 / world.scale_factor * world.pan_sensitivity;
+                let dy = input.mouse_diff().1 as f64 / world.scale_factor * world.pan_sensitivity;
+                world.x_offset -= dx/half_width /world.scale;
+                world.y_offset -= dy/half_height/world.scale;
             }
 
             // Update internal state and request a redraw
@@ -97,7 +252,59 @@ fn main() -> Result<(), Error> {
     });
 }
 
-fn log_error<E: std::error::Error + 'static>(method_name: &str, err: E) {
+#[cfg(not(target_arch = "wasm32"))]
+fn build_window(event_loop: &EventLoop<()>, config: &Config) -> Window {
+    let size = LogicalSize::new(config.width as f64, config.height as f64);
+    WindowBuilder::new()
+        .with_title("Mandelbrot")
+        .with_inner_size(size)
+        .with_min_inner_size(LogicalSize::new(1.0, 1.0))
+        .with_resizable(true)
+        .with_maximized(true)
+        .build(event_loop)
+        .unwrap()
+}
+
+#[cfg(target_arch = "wasm32")]
+fn build_window(event_loop: &EventLoop<()>, config: &Config) -> Result<Window, AppError> {
+    let size = LogicalSize::new(config.width as f64, config.height as f64);
+    let window = WindowBuilder::new()
+        .with_title("Mandelbrot")
+        .with_inner_size(size)
+        .with_min_inner_size(LogicalSize::new(1.0, 1.0))
+        .with_resizable(true)
+        .build(event_loop)
+        .unwrap();
+
+    // Attach the canvas to the page so winit can drive it, and let the
+    // browser own the element's lifetime.
+    let canvas = window.canvas();
+    web_sys::window()
+        .and_then(|win| win.document())
+        .and_then(|doc| doc.body())
+        .and_then(|body| body.append_child(&canvas).ok())
+        .ok_or_else(|| AppError::Web("couldn't append canvas to document body".to_string()))?;
+
+    Ok(window)
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+async fn build_pixels(window: &Window) -> Result<Pixels, Error> {
+    let window_size = window.inner_size();
+    let surface_texture = SurfaceTexture::new(window_size.width, window_size.height, window);
+    Pixels::new(window_size.width, window_size.height, surface_texture)
+}
+
+#[cfg(target_arch = "wasm32")]
+async fn build_pixels(window: &Window) -> Result<Pixels, Error> {
+    let window_size = window.inner_size();
+    let surface_texture = SurfaceTexture::new(window_size.width, window_size.height, window);
+    PixelsBuilder::new(window_size.width, window_size.height, surface_texture)
+        .build_async()
+        .await
+}
+
+pub(crate) fn log_error<E: std::error::Error + 'static>(method_name: &str, err: E) {
     error!("{method_name}() failed: {err}");
     for source in err.sources().skip(1) {
         error!("  Caused by: {source}");
@@ -111,32 +318,112 @@ fn square_complex(x: f64, y: f64) -> [f64; 2] {
 }
 
 impl World {
-    fn new() -> Self {
+    fn new(width: u32, height: u32, scale_factor: f64, config: &Config) -> Self {
         Self {
-            iteration_number: 255,
-            scale: 0.5,
-            x_offset: 0.0,
-            y_offset: 0.0,
+            width,
+            height,
+            scale_factor,
+            iteration_number: config.iteration_number,
+            scale: config.scale,
+            x_offset: config.x_offset,
+            y_offset: config.y_offset,
+            bailout: 1.0e6,
+            palette: config.palette,
+            zoom_in_factor: config.zoom_in_factor,
+            zoom_out_factor: config.zoom_out_factor,
+            pan_sensitivity: config.pan_sensitivity,
+            min_iteration_number: config.iteration_number,
+            max_iteration_number: config.iteration_number.saturating_mul(8),
+            last_frame: Instant::now(),
+            fps: 0.0,
+        }
+    }
+
+    fn logical_width(&self) -> f64 {
+        self.width as f64 / self.scale_factor
+    }
+
+    fn logical_height(&self) -> f64 {
+        self.height as f64 / self.scale_factor
+    }
+
+    fn screen_to_complex(&self, x: f64, y: f64) -> (f64, f64) {
+        let half_width = self.logical_width() / 2.0;
+        let half_height = self.logical_height() / 2.0;
+
+        let logical_x = x / self.scale_factor;
+        let logical_y = y / self.scale_factor;
+
+        let relative_x = logical_x/(half_width  * self.scale)+self.x_offset-(1.0 / self.scale) as f64;
+        let relative_y = logical_y/(half_height * self.scale)+self.y_offset-(1.0 / self.scale) as f64;
+
+        (relative_x, relative_y)
+    }
+
+    fn begin_frame(&mut self) {
+        let now = Instant::now();
+        let dt = now.duration_since(self.last_frame);
+        self.last_frame = now;
+        if dt.as_secs_f64() > 0.0 {
+            self.fps = 1.0 / dt.as_secs_f64();
+        }
+
+        if dt > TARGET_FRAME_BUDGET {
+            self.iteration_number = self
+                .iteration_number
+                .saturating_sub(16)
+                .max(self.min_iteration_number);
+        } else {
+            let depth_for_scale = self.min_iteration_number as f64
+                + self.scale.max(1.0).log2() * 64.0;
+            let depth_for_scale =
+                (depth_for_scale as u16).clamp(self.min_iteration_number, self.max_iteration_number);
+            if depth_for_scale > self.iteration_number {
+                self.iteration_number = (self.iteration_number + 8).min(depth_for_scale);
+            }
+        }
+    }
+
+    fn status_line(&self, cursor: Option<(f64, f64)>) -> String {
+        match cursor {
+            Some((x, y)) => format!(
+                "Mandelbrot — {:.0} fps | scale {:.3e} | iter {} | z = {:.6} {:+.6}i",
+                self.fps, self.scale, self.iteration_number, x, y
+            ),
+            None => format!(
+                "Mandelbrot — {:.0} fps | scale {:.3e} | iter {}",
+                self.fps, self.scale, self.iteration_number
+            ),
         }
     }
 
     fn draw(&self, frame: &mut [u8]) {
+        let half_width = self.logical_width() / 2.0;
+        let half_height = self.logical_height() / 2.0;
+
         frame.par_chunks_exact_mut(4).enumerate().for_each(|(i, pixel)| {
 
-            let x = i % WIDTH as usize;
-            let y = i / WIDTH as usize;
-            
-            let relative_x = x as f64/((HALF_WIDTH  as f64) * self.scale)+self.x_offset-(1.0 / self.scale) as f64;
-            let relative_y = y as f64/((HALF_HEIGHT as f64) * self.scale)+self.y_offset-(1.0 / self.scale) as f64;
+            let x = i % self.width as usize;
+            let y = i / self.width as usize;
+
+            let logical_x = x as f64 / self.scale_factor;
+            let logical_y = y as f64 / self.scale_factor;
+
+            let relative_x = logical_x/(half_width  * self.scale)+self.x_offset-(1.0 / self.scale) as f64;
+            let relative_y = logical_y/(half_height * self.scale)+self.y_offset-(1.0 / self.scale) as f64;
 
-            let c = self.calculate_mandelbrot([relative_x, relative_y]);
-            let rgba: [u8; 4] = [c, c, c, 255];
-            
-            pixel.copy_from_slice(&rgba);
+            let mu = self.calculate_mandelbrot([relative_x, relative_y]);
+            let [r, g, b] = match mu {
+                Some(mu) => self.palette.color(mu),
+                None => [0, 0, 0],
+            };
+
+            pixel.copy_from_slice(&[r, g, b, 255]);
         });
     }
 
-    fn calculate_mandelbrot(&self, c: [f64; 2]) -> u8 {
+    // None if `c` never escapes within iteration_number steps.
+    fn calculate_mandelbrot(&self, c: [f64; 2]) -> Option<f64> {
         let mut z: [f64; 2] = [0.0, 0.0];
         let mut x;
         let mut y;
@@ -144,11 +431,13 @@ impl World {
         for i in 0..self.iteration_number {
             x = z[0] + c[0];
             y = z[1] + c[1];
-            if ((x*x + y*y) as f64).sqrt() > 64.0 {
-                return (i*1).min(255) as u8;
+            let magnitude_sq = x*x + y*y;
+            if magnitude_sq > self.bailout*self.bailout {
+                let mu = i as f64 + 1.0 - (magnitude_sq.sqrt().ln()).ln()/2.0_f64.ln();
+                return Some(mu);
             }
             z = square_complex(x, y)
         }
-        return 0;
+        None
     }
-}
\ No newline at end of file
+}